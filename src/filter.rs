@@ -0,0 +1,200 @@
+
+use crate::{FrameKind, Id, Message};
+
+/// Narrows which frame kinds and ID widths a [`Filter`] accepts, independent of the ID value
+///
+/// Each field defaults to `None`, meaning "don't care".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameConstraint {
+    /// If set, only messages of this [`FrameKind`] are accepted
+    pub kind: Option<FrameKind>,
+    /// If set, only extended (`true`) or standard (`false`) IDs are accepted
+    pub extended: Option<bool>,
+}
+
+impl FrameConstraint {
+    /// A constraint that does not restrict anything
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    fn matches(&self, message: &Message) -> bool {
+        if let Some(kind) = self.kind {
+            if message.kind() != kind {
+                return false;
+            }
+        }
+        if let Some(extended) = self.extended {
+            if message.id().is_extended() != extended {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A single receive-side acceptance filter
+///
+/// Modeled on the two matching modes common to CAN controller acceptance filter banks: mask
+/// filtering and range filtering, each computed on the expanded 29-bit ID value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Accepts IDs for which `id & mask == code & mask`
+    Mask {
+        /// The bits of the ID that are compared
+        mask: u32,
+        /// The required value of the bits selected by `mask`
+        code: u32,
+        /// Additional constraints on frame kind and ID width
+        constraint: FrameConstraint,
+    },
+    /// Accepts IDs in the inclusive range `low..=high`
+    Range {
+        /// The lowest ID accepted
+        low: Id,
+        /// The highest ID accepted
+        high: Id,
+        /// Additional constraints on frame kind and ID width
+        constraint: FrameConstraint,
+    },
+}
+
+impl Filter {
+    /// Creates a mask filter that accepts any ID for which `id & mask == code & mask`
+    pub fn mask(mask: u32, code: u32) -> Self {
+        Filter::Mask {
+            mask: mask,
+            code: code,
+            constraint: FrameConstraint::any(),
+        }
+    }
+
+    /// Creates a range filter that accepts any ID in `low..=high`
+    pub fn range(low: Id, high: Id) -> Self {
+        Filter::Range {
+            low: low,
+            high: high,
+            constraint: FrameConstraint::any(),
+        }
+    }
+
+    /// Returns a copy of this filter with its frame/ID-width constraint replaced
+    pub fn with_constraint(self, constraint: FrameConstraint) -> Self {
+        match self {
+            Filter::Mask { mask, code, .. } => Filter::Mask {
+                mask: mask,
+                code: code,
+                constraint: constraint,
+            },
+            Filter::Range { low, high, .. } => Filter::Range {
+                low: low,
+                high: high,
+                constraint: constraint,
+            },
+        }
+    }
+
+    /// Returns true if this filter accepts `message`
+    pub fn accepts(&self, message: &Message) -> bool {
+        match *self {
+            Filter::Mask {
+                mask,
+                code,
+                constraint,
+            } => constraint.matches(message) && (message.id().as_extended() & mask) == (code & mask),
+            Filter::Range {
+                low,
+                high,
+                constraint,
+            } => constraint.matches(message) && low <= message.id() && message.id() <= high,
+        }
+    }
+}
+
+/// A set of acceptance filters, together emulating a hardware CAN controller's acceptance filter
+/// bank in software
+///
+/// A message is accepted if any filter in the set accepts it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilterSet {
+    filters: Vec<Filter>,
+}
+
+impl FilterSet {
+    /// Creates an empty filter set, which accepts no messages
+    pub fn new() -> Self {
+        FilterSet {
+            filters: Vec::new(),
+        }
+    }
+
+    /// Adds a filter to this set
+    pub fn push(&mut self, filter: Filter) {
+        self.filters.push(filter);
+    }
+
+    /// Returns true if any filter in this set accepts `message`
+    pub fn accepts(&self, message: &Message) -> bool {
+        self.filters.iter().any(|filter| filter.accepts(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StandardId;
+
+    fn data_message(id: u16) -> Message {
+        Message::new(id, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_mask_filter_matches() {
+        // Accept only IDs with the low bit set
+        let filter = Filter::mask(0x1, 0x1);
+        assert!(filter.accepts(&data_message(0x001)));
+        assert!(filter.accepts(&data_message(0x7ff)));
+        assert!(!filter.accepts(&data_message(0x002)));
+    }
+
+    #[test]
+    fn test_range_filter_matches() {
+        let filter = Filter::range(Id::Short(StandardId::new(0x10).unwrap()), Id::Short(StandardId::new(0x20).unwrap()));
+        assert!(filter.accepts(&data_message(0x10)));
+        assert!(filter.accepts(&data_message(0x20)));
+        assert!(filter.accepts(&data_message(0x18)));
+        assert!(!filter.accepts(&data_message(0x0f)));
+        assert!(!filter.accepts(&data_message(0x21)));
+    }
+
+    #[test]
+    fn test_constraint_frame_kind() {
+        let filter = Filter::mask(0, 0).with_constraint(FrameConstraint {
+            kind: Some(FrameKind::Remote),
+            extended: None,
+        });
+        let remote = Message::new_remote(1u16, 0).unwrap();
+        assert!(filter.accepts(&remote));
+        assert!(!filter.accepts(&data_message(1)));
+    }
+
+    #[test]
+    fn test_constraint_id_width() {
+        let filter = Filter::mask(0, 0).with_constraint(FrameConstraint {
+            kind: None,
+            extended: Some(true),
+        });
+        let extended = Message::new(0x10u32, &[]).unwrap();
+        assert!(filter.accepts(&extended));
+        assert!(!filter.accepts(&data_message(0x10)));
+    }
+
+    #[test]
+    fn test_filter_set_any_match() {
+        let mut set = FilterSet::new();
+        assert!(!set.accepts(&data_message(5)));
+        set.push(Filter::mask(0x7ff, 5));
+        assert!(set.accepts(&data_message(5)));
+        assert!(!set.accepts(&data_message(6)));
+    }
+}
@@ -15,6 +15,12 @@ extern crate quick_error;
 
 mod message;
 pub use message::*;
+mod signal;
+pub use signal::*;
+mod filter;
+pub use filter::*;
+mod wire;
+pub use wire::*;
 
 /// A trait for things that can send and receive CAN messages
 pub trait Can {
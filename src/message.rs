@@ -1,31 +1,111 @@
 
 use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 
 /// 11-bit ID mask
 const SHORT_MASK: u16 = 0x7ff;
 /// 29-bit ID mask
 const EXTENDED_MASK: u32 = 0x1fffffff;
 
+/// A validated standard (11-bit) CAN identifier
+///
+/// A `StandardId` is guaranteed to fit in 11 bits; it cannot be constructed with an out-of-range
+/// value except through the `unsafe` `new_unchecked`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StandardId(u16);
+
+impl StandardId {
+    /// The ID 0
+    pub const ZERO: StandardId = StandardId(0);
+    /// The largest valid standard ID, 0x7ff
+    pub const MAX: StandardId = StandardId(SHORT_MASK);
+
+    /// Creates a standard ID, or returns `None` if `id` does not fit in 11 bits
+    pub const fn new(id: u16) -> Option<StandardId> {
+        if id & !SHORT_MASK == 0 {
+            Some(StandardId(id))
+        } else {
+            None
+        }
+    }
+
+    /// Creates a standard ID without checking that it fits in 11 bits
+    ///
+    /// # Safety
+    /// The caller must ensure that `id & !0x7ff == 0`. Other code may assume that every
+    /// `StandardId` fits in 11 bits.
+    pub const unsafe fn new_unchecked(id: u16) -> StandardId {
+        StandardId(id)
+    }
+
+    /// Returns the value of this ID
+    pub const fn as_u16(self) -> u16 {
+        self.0
+    }
+}
+
+/// A validated extended (29-bit) CAN identifier
+///
+/// An `ExtendedId` is guaranteed to fit in 29 bits; it cannot be constructed with an out-of-range
+/// value except through the `unsafe` `new_unchecked`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExtendedId(u32);
+
+impl ExtendedId {
+    /// The ID 0
+    pub const ZERO: ExtendedId = ExtendedId(0);
+    /// The largest valid extended ID, 0x1fffffff
+    pub const MAX: ExtendedId = ExtendedId(EXTENDED_MASK);
+
+    /// Creates an extended ID, or returns `None` if `id` does not fit in 29 bits
+    pub const fn new(id: u32) -> Option<ExtendedId> {
+        if id & !EXTENDED_MASK == 0 {
+            Some(ExtendedId(id))
+        } else {
+            None
+        }
+    }
+
+    /// Creates an extended ID without checking that it fits in 29 bits
+    ///
+    /// # Safety
+    /// The caller must ensure that `id & !0x1fffffff == 0`. Other code may assume that every
+    /// `ExtendedId` fits in 29 bits.
+    pub const unsafe fn new_unchecked(id: u32) -> ExtendedId {
+        ExtendedId(id)
+    }
+
+    /// Returns the value of this ID
+    pub const fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
 /// A CAN message identifier
 ///
 /// Message identifiers can be compared and ordered by their underlying values, regardless of
 /// whether they are short or extended.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub enum Id {
     /// A short CAN ID of up to 11 bits
-    Short(u16),
+    Short(StandardId),
     /// An extended CAN ID of up to 29 bits
-    Extended(u32),
+    Extended(ExtendedId),
 }
 
 impl Id {
     /// Returns the value of this ID as a u32. If this ID is short, it is expanded.
-    fn as_extended(&self) -> u32 {
+    pub fn as_extended(&self) -> u32 {
         match *self {
-            Id::Short(short) => short.into(),
-            Id::Extended(extended) => extended,
+            Id::Short(short) => short.as_u16().into(),
+            Id::Extended(extended) => extended.as_u32(),
         }
     }
+
+    /// Returns true if this is an extended (29-bit) ID, or false if it is a standard (11-bit) ID
+    pub fn is_extended(&self) -> bool {
+        matches!(*self, Id::Extended(_))
+    }
 }
 
 impl PartialEq for Id {
@@ -48,27 +128,63 @@ impl Ord for Id {
     }
 }
 
-impl Id {
-    /// Checks if this ID fits in the specified numbers of bits
-    fn is_valid(&self) -> bool {
-        match *self {
-            Id::Short(id) => (id & !SHORT_MASK) == 0,
-            Id::Extended(id) => (id & !EXTENDED_MASK) == 0,
-        }
+impl Hash for Id {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash the expanded value so that Short and Extended IDs that compare equal also hash
+        // equal
+        self.as_extended().hash(state);
     }
 }
 
+impl From<StandardId> for Id {
+    fn from(input: StandardId) -> Self {
+        Id::Short(input)
+    }
+}
+impl From<ExtendedId> for Id {
+    fn from(input: ExtendedId) -> Self {
+        Id::Extended(input)
+    }
+}
 impl From<u16> for Id {
+    /// Converts a raw standard ID value to an `Id`, masking off any bits beyond the low 11
+    ///
+    /// Use `StandardId::new` plus `Id::from` instead if out-of-range values should be rejected.
     fn from(input: u16) -> Self {
-        Id::Short(input)
+        Id::Short(StandardId(input & SHORT_MASK))
     }
 }
 impl From<u32> for Id {
+    /// Converts a raw extended ID value to an `Id`, masking off any bits beyond the low 29
+    ///
+    /// Use `ExtendedId::new` plus `Id::from` instead if out-of-range values should be rejected.
     fn from(input: u32) -> Self {
-        Id::Extended(input)
+        Id::Extended(ExtendedId(input & EXTENDED_MASK))
     }
 }
 
+/// Checks whether `length` is a valid Classical CAN data length (0 to 8 bytes)
+fn is_valid_classic_length(length: u8) -> bool {
+    length <= 8
+}
+
+/// Checks whether `length` is one of the discrete data lengths that CAN FD encodes in its DLC
+/// field (0 through 8, then 12, 16, 20, 24, 32, 48 and 64)
+fn is_valid_fd_length(length: u8) -> bool {
+    matches!(length, 0..=8 | 12 | 16 | 20 | 24 | 32 | 48 | 64)
+}
+
+/// Distinguishes a data frame, which carries a payload, from a remote transmission request
+/// frame, which carries no payload but still specifies a requested data length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// A data frame, carrying up to `length` bytes of payload
+    Data,
+    /// A remote transmission request frame, carrying no payload but requesting `length` bytes
+    /// from the node that owns the ID
+    Remote,
+}
+
 ///
 /// A CAN message
 ///
@@ -76,61 +192,159 @@ impl From<u32> for Id {
 pub struct Message {
     /// The message ID
     id: Id,
-    /// The length of the message, up to 8
+    /// Whether this is a data frame or a remote transmission request
+    kind: FrameKind,
+    /// The length of the message
     length: u8,
+    /// True if this is a CAN FD message, false if it is a Classical CAN message
+    fd: bool,
+    /// The Bit Rate Switch flag (CAN FD only)
+    brs: bool,
+    /// The Error State Indicator flag (CAN FD only)
+    esi: bool,
     /// The data in this message
-    data: [u8; 8],
+    data: [u8; 64],
 }
 
 impl Message {
-    /// Creates a message with a provided ID and data
+    /// Creates a Classical CAN message with a provided ID and data
+    ///
+    /// Returns an error if `data` contains more than 8 bytes.
     pub fn new<I: Into<Id>>(id: I, data: &[u8]) -> Result<Self, RangeError> {
-        let id = id.into();
-        if data.len() <= 8 {
-            if id.is_valid() {
-                let mut message = Message {
-                    id: id,
-                    length: data.len() as u8,
-                    data: [0; 8],
-                };
-                // Copy data
-                for i in 0..data.len() {
-                    message.data[i] = data[i];
-                }
+        Self::with_flags(id, FrameKind::Data, data, false, false, false)
+    }
+
+    /// Creates a CAN FD message with a provided ID and data
+    ///
+    /// `data.len()` must be one of the lengths that CAN FD can encode in its DLC field: 0 through
+    /// 8, 12, 16, 20, 24, 32, 48, or 64. `brs` and `esi` set the Bit Rate Switch and Error State
+    /// Indicator flags.
+    ///
+    /// Returns an error if `data` is not a valid CAN FD length.
+    pub fn new_fd<I: Into<Id>>(
+        id: I,
+        data: &[u8],
+        brs: bool,
+        esi: bool,
+    ) -> Result<Self, RangeError> {
+        Self::with_flags(id, FrameKind::Data, data, true, brs, esi)
+    }
+
+    /// Creates a remote transmission request (RTR) frame with a provided ID, requesting `dlc`
+    /// bytes from the node that owns `id`
+    ///
+    /// The returned message carries no payload; its `data()` is always empty.
+    ///
+    /// Returns an error if `dlc` is greater than 8.
+    pub fn new_remote<I: Into<Id>>(id: I, dlc: u8) -> Result<Self, RangeError> {
+        Self::with_flags(id, FrameKind::Remote, &[], false, false, false)
+            .and_then(|mut message| {
+                message.set_len(dlc)?;
                 Ok(message)
-            } else {
-                Err(RangeError::IdLength)
-            }
-        } else {
-            Err(RangeError::DataLength)
-        }
+            })
     }
 
     /// Creates a message with a provided short ID and data
+    ///
+    /// Returns an error if `id` does not fit in 11 bits or `data` contains more than 8 bytes.
     pub fn with_short_id(id: u16, data: &[u8]) -> Result<Self, RangeError> {
+        let id = StandardId::new(id).ok_or(RangeError::IdLength)?;
         Self::new(id, data)
     }
     /// Creates a message with a provided extended ID and data
+    ///
+    /// Returns an error if `id` does not fit in 29 bits or `data` contains more than 8 bytes.
     pub fn with_extended_id(id: u32, data: &[u8]) -> Result<Self, RangeError> {
+        let id = ExtendedId::new(id).ok_or(RangeError::IdLength)?;
         Self::new(id, data)
     }
 
+    /// Shared constructor used by `new`, `new_fd`, and `new_remote`
+    fn with_flags<I: Into<Id>>(
+        id: I,
+        kind: FrameKind,
+        data: &[u8],
+        fd: bool,
+        brs: bool,
+        esi: bool,
+    ) -> Result<Self, RangeError> {
+        let id = id.into();
+        if data.len() <= 64 && Self::is_valid_length(data.len() as u8, fd) {
+            let mut message = Message {
+                id: id,
+                kind: kind,
+                length: data.len() as u8,
+                fd: fd,
+                brs: brs,
+                esi: esi,
+                data: [0; 64],
+            };
+            // Copy data
+            for i in 0..data.len() {
+                message.data[i] = data[i];
+            }
+            Ok(message)
+        } else {
+            Err(RangeError::DataLength)
+        }
+    }
+
+    /// Checks whether `length` is valid for a message with the given FD flag
+    fn is_valid_length(length: u8, fd: bool) -> bool {
+        if fd {
+            is_valid_fd_length(length)
+        } else {
+            is_valid_classic_length(length)
+        }
+    }
+
+    /// Returns true if this message is a remote transmission request
+    pub fn is_remote(&self) -> bool {
+        self.kind == FrameKind::Remote
+    }
+
+    /// Returns this message's frame kind
+    pub fn kind(&self) -> FrameKind {
+        self.kind
+    }
+
     /// Returns the ID of this message
     pub fn id(&self) -> Id {
-        self.id.clone()
+        self.id
     }
 
     /// Returns the length of this message
+    ///
+    /// For a remote transmission request, this is the requested data length, even though
+    /// `data()` is always empty.
     pub fn len(&self) -> u8 {
         self.length
     }
 
+    /// Returns true if this is a CAN FD message, or false if it is a Classical CAN message
+    pub fn is_fd(&self) -> bool {
+        self.fd
+    }
+
+    /// Returns the state of the Bit Rate Switch flag (CAN FD only, always false for Classical CAN
+    /// messages)
+    pub fn brs(&self) -> bool {
+        self.brs
+    }
+
+    /// Returns the state of the Error State Indicator flag (CAN FD only, always false for
+    /// Classical CAN messages)
+    pub fn esi(&self) -> bool {
+        self.esi
+    }
+
     /// Sets the length of this message
     ///
     /// If the new length is greater than the current length, the new bytes are set to zero.
-    /// Returns an error if length is greater than 8
+    /// Returns an error if the new length is not valid for this message's frame type (0 to 8 for
+    /// Classical CAN, or one of the discrete CAN FD lengths for CAN FD)
     pub fn set_len(&mut self, length: u8) -> Result<(), RangeError> {
-        if length <= 8 {
+        if Self::is_valid_length(length, self.fd) {
             // Fill with zeroes
             for i in self.length..length {
                 self.data[usize::from(i)] = 0;
@@ -143,12 +357,26 @@ impl Message {
     }
 
     /// Returns a reference to the data of this message
+    ///
+    /// This is always empty for a remote transmission request.
     pub fn data(&self) -> &[u8] {
-        &self.data[..usize::from(self.length)]
+        &self.data[..usize::from(self.data_len())]
     }
     /// Returns a mutable reference to the data of this message
+    ///
+    /// This is always empty for a remote transmission request.
     pub fn data_mut(&mut self) -> &mut [u8] {
-        &mut self.data[..usize::from(self.length)]
+        let len = usize::from(self.data_len());
+        &mut self.data[..len]
+    }
+
+    /// Returns the number of data bytes actually stored in `self.data`: `self.length` for a data
+    /// frame, or 0 for a remote transmission request
+    fn data_len(&self) -> u8 {
+        match self.kind {
+            FrameKind::Data => self.length,
+            FrameKind::Remote => 0,
+        }
     }
 }
 
@@ -169,30 +397,53 @@ mod tests {
 
     #[test]
     fn test_zero_valid_short() {
-        assert!(Id::Short(0).is_valid());
+        assert!(StandardId::new(0).is_some());
     }
     #[test]
     fn test_zero_valid_extended() {
-        assert!(Id::Extended(0).is_valid());
+        assert!(ExtendedId::new(0).is_some());
     }
     #[test]
     fn test_max_valid_short() {
-        assert!(Id::Short(0b11111111111).is_valid());
+        assert!(StandardId::new(0b11111111111).is_some());
     }
     #[test]
     fn test_max_valid_extended() {
-        assert!(Id::Extended(0b11111111111111111111111111111).is_valid());
+        assert!(ExtendedId::new(0b11111111111111111111111111111).is_some());
     }
     #[test]
     fn test_beyond_invalid_short() {
-        assert!(!Id::Short(0b11111111111 + 1).is_valid());
+        assert!(StandardId::new(0b11111111111 + 1).is_none());
     }
     #[test]
     fn test_beyond_invalid_extended() {
-        assert!(!Id::Extended(0b11111111111111111111111111111 + 1).is_valid());
+        assert!(ExtendedId::new(0b11111111111111111111111111111 + 1).is_none());
+    }
+    #[test]
+    fn test_standard_id_constants() {
+        assert_eq!(0, StandardId::ZERO.as_u16());
+        assert_eq!(0b11111111111, StandardId::MAX.as_u16());
+    }
+    #[test]
+    fn test_extended_id_constants() {
+        assert_eq!(0, ExtendedId::ZERO.as_u32());
+        assert_eq!(0b11111111111111111111111111111, ExtendedId::MAX.as_u32());
+    }
+    #[test]
+    fn test_id_hash_matches_eq() {
+        use std::collections::hash_map::DefaultHasher;
+        fn hash_of(id: Id) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            id.hash(&mut hasher);
+            hasher.finish()
+        }
+        let short = Id::Short(StandardId::new(5).unwrap());
+        let extended = Id::Extended(ExtendedId::new(5).unwrap());
+        assert_eq!(short, extended);
+        assert_eq!(hash_of(short), hash_of(extended));
     }
 
-    const ID: Id = Id::Short(1);
+    const ID: Id = Id::Short(StandardId(1));
 
     #[test]
     fn test_data_empty() {
@@ -214,4 +465,59 @@ mod tests {
         let expected: Result<Message, RangeError> = Err(RangeError::DataLength);
         assert_eq!(expected, message);
     }
+
+    #[test]
+    fn test_fd_valid_lengths() {
+        for &length in &[0, 1, 8, 12, 16, 20, 24, 32, 48, 64] {
+            let data = vec![0u8; length];
+            assert!(Message::new_fd(ID, &data, false, false).is_ok());
+        }
+    }
+    #[test]
+    fn test_fd_invalid_length() {
+        let data = vec![0u8; 9];
+        let message = Message::new_fd(ID, &data, false, false);
+        let expected: Result<Message, RangeError> = Err(RangeError::DataLength);
+        assert_eq!(expected, message);
+    }
+    #[test]
+    fn test_fd_flags() {
+        let message = Message::new_fd(ID, &[1, 2, 3], true, true).unwrap();
+        assert!(message.is_fd());
+        assert!(message.brs());
+        assert!(message.esi());
+    }
+    #[test]
+    fn test_classic_not_fd() {
+        let message = Message::new(ID, &[1, 2, 3]).unwrap();
+        assert!(!message.is_fd());
+        assert!(!message.brs());
+        assert!(!message.esi());
+    }
+
+    #[test]
+    fn test_remote_is_remote() {
+        let message = Message::new_remote(ID, 4).unwrap();
+        assert!(message.is_remote());
+        assert_eq!(FrameKind::Remote, message.kind());
+    }
+    #[test]
+    fn test_remote_data_empty() {
+        let message = Message::new_remote(ID, 8).unwrap();
+        assert_eq!(8, message.len());
+        let expected_data: [u8; 0] = [];
+        assert_eq!(&expected_data, message.data());
+    }
+    #[test]
+    fn test_remote_dlc_too_long() {
+        let message = Message::new_remote(ID, 9);
+        let expected: Result<Message, RangeError> = Err(RangeError::DataLength);
+        assert_eq!(expected, message);
+    }
+    #[test]
+    fn test_data_not_remote() {
+        let message = Message::new(ID, &[1, 2, 3]).unwrap();
+        assert!(!message.is_remote());
+        assert_eq!(FrameKind::Data, message.kind());
+    }
 }
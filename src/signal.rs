@@ -0,0 +1,379 @@
+
+use std::convert::TryFrom;
+
+use crate::Message;
+
+/// The bit order used to lay out a [`Signal`](struct.Signal.html) within a message's data bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Big-endian ("Motorola") bit numbering
+    ///
+    /// `start_bit` is the most significant bit of the signal. Bit numbering counts MSB-first
+    /// within each byte, so after the least significant bit of a byte is reached the signal
+    /// continues at the most significant bit of the next byte.
+    BigEndian,
+    /// Little-endian ("Intel") bit numbering
+    ///
+    /// `start_bit` is the least significant bit of the signal, and the signal continues toward
+    /// more significant bits, wrapping into the next byte as needed.
+    LittleEndian,
+}
+
+/// A description of a named signal packed into a fixed-layout CAN message
+///
+/// A signal occupies `bit_len` bits starting at `start_bit`, interpreted according to
+/// `byte_order`. The raw integer extracted from those bits is sign-extended if `signed`, then
+/// converted to a physical value as `raw * factor + offset`; `encode` inverts this conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    /// The position of the signal's first bit, in `byte_order`-specific bit numbering
+    pub start_bit: u16,
+    /// The number of bits the signal occupies, up to 64
+    pub bit_len: u8,
+    /// The bit order used to locate the signal's bits within the message data
+    pub byte_order: ByteOrder,
+    /// The value that the raw integer is multiplied by to produce the physical value
+    pub factor: f64,
+    /// The value added to the scaled raw integer to produce the physical value
+    pub offset: f64,
+    /// True if the raw integer should be interpreted as two's-complement signed
+    pub signed: bool,
+}
+
+impl Signal {
+    /// Reads this signal's bits out of `message` and returns its physical value
+    ///
+    /// Bits that fall beyond the end of `message`'s data are read as zero.
+    pub fn decode(&self, message: &Message) -> f64 {
+        let raw = self.extract_raw(message.data());
+        let raw = if self.signed {
+            self.sign_extend(raw)
+        } else {
+            raw as i64
+        };
+        (raw as f64) * self.factor + self.offset
+    }
+
+    /// Converts `value` to this signal's raw representation and writes it into `message`,
+    /// leaving bits belonging to other signals untouched
+    ///
+    /// Returns an error if this signal's bits extend beyond `message`'s current length.
+    pub fn encode(&self, message: &mut Message, value: f64) -> Result<(), SignalError> {
+        if self.max_byte_index() >= Some(message.data().len() as u32) {
+            return Err(SignalError::OutOfRange);
+        }
+        let scaled = ((value - self.offset) / self.factor).round();
+        let raw = (scaled as i64 as u64) & self.mask();
+        self.inject_raw(message.data_mut(), raw);
+        Ok(())
+    }
+
+    /// The number of bits actually used, clamped to 64 so that shifts never overflow
+    fn effective_bit_len(&self) -> u8 {
+        self.bit_len.min(64)
+    }
+
+    /// A mask with `effective_bit_len()` low bits set
+    fn mask(&self) -> u64 {
+        let len = self.effective_bit_len();
+        if len == 0 {
+            0
+        } else if len == 64 {
+            u64::max_value()
+        } else {
+            (1u64 << len) - 1
+        }
+    }
+
+    /// The physical bit positions this signal occupies, ordered from the signal's least
+    /// significant bit to its most significant bit
+    ///
+    /// Computed as `u32` (rather than `u16`, which `start_bit` is stored as) so that a
+    /// `start_bit`/`bit_len` combination near the top of the `u16` range cannot overflow.
+    fn bit_positions(&self) -> Vec<u32> {
+        let len = self.effective_bit_len();
+        let start_bit = u32::from(self.start_bit);
+        match self.byte_order {
+            ByteOrder::LittleEndian => (0..u32::from(len)).map(|i| start_bit + i).collect(),
+            ByteOrder::BigEndian => {
+                let mut positions = Vec::with_capacity(usize::from(len));
+                let mut byte_index = start_bit / 8;
+                let mut bit_index = start_bit % 8;
+                for _ in 0..len {
+                    positions.push(byte_index * 8 + bit_index);
+                    if bit_index == 0 {
+                        byte_index += 1;
+                        bit_index = 7;
+                    } else {
+                        bit_index -= 1;
+                    }
+                }
+                // `positions` currently runs from the MSB (start_bit) to the LSB; reverse it so
+                // that index 0 is the LSB, matching the little-endian case
+                positions.reverse();
+                positions
+            }
+        }
+    }
+
+    /// The index of the last data byte this signal's bits fall in, or `None` if `bit_len` is 0
+    fn max_byte_index(&self) -> Option<u32> {
+        self.bit_positions().into_iter().map(|bit| bit / 8).max()
+    }
+
+    /// Reads this signal's bits out of `data`, treating any bit beyond the end of `data` as zero
+    fn extract_raw(&self, data: &[u8]) -> u64 {
+        let mut raw = 0u64;
+        for (i, bit) in self.bit_positions().into_iter().enumerate() {
+            let byte = data.get(usize::try_from(bit / 8).unwrap()).copied().unwrap_or(0);
+            let value = (byte >> (bit % 8)) & 1;
+            raw |= u64::from(value) << i;
+        }
+        raw
+    }
+
+    /// Writes `raw`'s low `effective_bit_len()` bits into `data` at this signal's bit positions,
+    /// leaving all other bits unchanged
+    fn inject_raw(&self, data: &mut [u8], raw: u64) {
+        for (i, bit) in self.bit_positions().into_iter().enumerate() {
+            let byte_index = usize::try_from(bit / 8).unwrap();
+            let bit_index = bit % 8;
+            if (raw >> i) & 1 == 1 {
+                data[byte_index] |= 1 << bit_index;
+            } else {
+                data[byte_index] &= !(1 << bit_index);
+            }
+        }
+    }
+
+    /// Sign-extends the low `effective_bit_len()` bits of `raw` to a full `i64`
+    fn sign_extend(&self, raw: u64) -> i64 {
+        let len = self.effective_bit_len();
+        if len == 0 {
+            0
+        } else if len == 64 {
+            raw as i64
+        } else {
+            let shift = 64 - u32::from(len);
+            ((raw << shift) as i64) >> shift
+        }
+    }
+}
+
+quick_error! {
+    /// Errors returned while encoding a `Signal` into a `Message`
+    #[derive(Debug, PartialEq)]
+    pub enum SignalError {
+        /// The signal's bits extend beyond the message's current length
+        OutOfRange {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Message;
+
+    fn msg(data: &[u8]) -> Message {
+        Message::new(1u16, data).unwrap()
+    }
+
+    #[test]
+    fn test_decode_little_endian_unsigned() {
+        let signal = Signal {
+            start_bit: 0,
+            bit_len: 8,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        assert_eq!(0x42, signal.decode(&msg(&[0x42])) as i64);
+    }
+
+    #[test]
+    fn test_decode_little_endian_spans_bytes() {
+        // 12-bit value spanning bytes 0 and 1
+        let signal = Signal {
+            start_bit: 4,
+            bit_len: 12,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        // byte0 = 0xF0 (bits 4..7 = 0xF), byte1 = 0x0A (bits 0..3 = 0xA)
+        // raw = 0xA_F = 0xAF = 175
+        let value = signal.decode(&msg(&[0xF0, 0x0A]));
+        assert_eq!(0xAF as i64, value as i64);
+    }
+
+    #[test]
+    fn test_decode_big_endian_single_byte() {
+        let signal = Signal {
+            start_bit: 7,
+            bit_len: 8,
+            byte_order: ByteOrder::BigEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        assert_eq!(0x42, signal.decode(&msg(&[0x42])) as i64);
+    }
+
+    #[test]
+    fn test_decode_big_endian_spans_bytes() {
+        let signal = Signal {
+            start_bit: 7,
+            bit_len: 16,
+            byte_order: ByteOrder::BigEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        assert_eq!(0x1234, signal.decode(&msg(&[0x12, 0x34])) as i64);
+    }
+
+    #[test]
+    fn test_decode_signed_negative() {
+        let signal = Signal {
+            start_bit: 0,
+            bit_len: 8,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: true,
+        };
+        assert_eq!(-1.0, signal.decode(&msg(&[0xFF])));
+        assert_eq!(-2.0, signal.decode(&msg(&[0xFE])));
+    }
+
+    #[test]
+    fn test_decode_scale_and_offset() {
+        let signal = Signal {
+            start_bit: 0,
+            bit_len: 8,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 0.5,
+            offset: -10.0,
+            signed: false,
+        };
+        assert_eq!(-10.0, signal.decode(&msg(&[0])));
+        assert_eq!(40.0, signal.decode(&msg(&[100])));
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip_little_endian() {
+        let signal = Signal {
+            start_bit: 4,
+            bit_len: 12,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let mut message = msg(&[0, 0]);
+        signal.encode(&mut message, 0xABC as f64).unwrap();
+        assert_eq!(0xABC as i64, signal.decode(&message) as i64);
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip_big_endian() {
+        let signal = Signal {
+            start_bit: 7,
+            bit_len: 16,
+            byte_order: ByteOrder::BigEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let mut message = msg(&[0, 0]);
+        signal.encode(&mut message, 0x1234 as f64).unwrap();
+        assert_eq!(0x1234 as i64, signal.decode(&message) as i64);
+    }
+
+    #[test]
+    fn test_encode_does_not_disturb_neighbors() {
+        let low = Signal {
+            start_bit: 0,
+            bit_len: 4,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let high = Signal {
+            start_bit: 4,
+            bit_len: 4,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let mut message = msg(&[0]);
+        low.encode(&mut message, 0xF as f64).unwrap();
+        high.encode(&mut message, 0x3 as f64).unwrap();
+        assert_eq!(0xF, low.decode(&message) as i64);
+        assert_eq!(0x3, high.decode(&message) as i64);
+        assert_eq!(&[0x3F], message.data());
+    }
+
+    #[test]
+    fn test_encode_out_of_range() {
+        let signal = Signal {
+            start_bit: 0,
+            bit_len: 16,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let mut message = msg(&[0]);
+        let result = signal.encode(&mut message, 1.0);
+        assert_eq!(Err(SignalError::OutOfRange), result);
+    }
+
+    #[test]
+    fn test_encode_remote_rejected() {
+        let signal = Signal {
+            start_bit: 0,
+            bit_len: 8,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let mut message = Message::new_remote(1u16, 8).unwrap();
+        let result = signal.encode(&mut message, 1.0);
+        assert_eq!(Err(SignalError::OutOfRange), result);
+    }
+
+    #[test]
+    fn test_decode_large_start_bit_does_not_overflow() {
+        // A start_bit/bit_len combination near the top of the u16 range must not panic; it falls
+        // entirely beyond any realistic message, so it should just decode as zero.
+        let signal = Signal {
+            start_bit: 65500,
+            bit_len: 64,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        assert_eq!(0.0, signal.decode(&msg(&[1, 2, 3])));
+    }
+
+    #[test]
+    fn test_encode_large_start_bit_out_of_range() {
+        let signal = Signal {
+            start_bit: 65500,
+            bit_len: 64,
+            byte_order: ByteOrder::LittleEndian,
+            factor: 1.0,
+            offset: 0.0,
+            signed: false,
+        };
+        let mut message = msg(&[1, 2, 3]);
+        assert_eq!(Err(SignalError::OutOfRange), signal.encode(&mut message, 1.0));
+    }
+}
@@ -0,0 +1,176 @@
+
+use std::convert::TryFrom;
+
+use bytes::{Buf, BufMut};
+
+use crate::{ExtendedId, Id, Message, StandardId};
+
+/// Set in the header byte if this message is a remote transmission request
+const HEADER_REMOTE: u8 = 0b0000_0001;
+/// Set in the header byte if this message's ID is extended (29-bit) rather than standard
+const HEADER_EXTENDED: u8 = 0b0000_0010;
+/// Set in the header byte if this message is a CAN FD frame
+const HEADER_FD: u8 = 0b0000_0100;
+/// Set in the header byte if this message's Bit Rate Switch flag is set
+const HEADER_BRS: u8 = 0b0000_1000;
+/// Set in the header byte if this message's Error State Indicator flag is set
+const HEADER_ESI: u8 = 0b0001_0000;
+
+/// The number of header, ID, and DLC bytes that precede the payload in the wire format
+const PREFIX_LEN: usize = 6;
+
+impl Message {
+    /// Writes this message to `buf` in this crate's canonical binary wire format
+    ///
+    /// The format is a header byte (frame kind, ID width, and CAN FD flags), a 4-byte ID
+    /// (expanded to its full value), a DLC byte giving `self.len()`, and finally the payload
+    /// bytes (`self.data()`, which is empty for a remote transmission request).
+    ///
+    /// This gives `Can` implementations backed by different transports (SocketCAN, SLCAN, USB
+    /// adapters) a shared encoding, rather than each reinventing a byte layout.
+    pub fn write_to<B: BufMut>(&self, buf: &mut B) {
+        let mut header = 0u8;
+        if self.is_remote() {
+            header |= HEADER_REMOTE;
+        }
+        if self.id().is_extended() {
+            header |= HEADER_EXTENDED;
+        }
+        if self.is_fd() {
+            header |= HEADER_FD;
+        }
+        if self.brs() {
+            header |= HEADER_BRS;
+        }
+        if self.esi() {
+            header |= HEADER_ESI;
+        }
+        buf.put_u8(header);
+        buf.put_u32(self.id().as_extended());
+        buf.put_u8(self.len());
+        buf.put_slice(self.data());
+    }
+
+    /// Reads a message from `buf` that was previously written with `write_to`
+    ///
+    /// Returns an error if `buf` does not contain enough bytes, or the bytes it contains do not
+    /// describe a valid message.
+    pub fn read_from<B: Buf>(buf: &mut B) -> Result<Message, ParseError> {
+        if buf.remaining() < PREFIX_LEN {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let header = buf.get_u8();
+        let id_value = buf.get_u32();
+        let dlc = buf.get_u8();
+
+        let id = if header & HEADER_EXTENDED != 0 {
+            Id::from(ExtendedId::new(id_value).ok_or(ParseError::InvalidId)?)
+        } else {
+            let short = u16::try_from(id_value).map_err(|_| ParseError::InvalidId)?;
+            Id::from(StandardId::new(short).ok_or(ParseError::InvalidId)?)
+        };
+
+        if header & HEADER_REMOTE != 0 {
+            Message::new_remote(id, dlc).map_err(|_| ParseError::InvalidLength)
+        } else {
+            if usize::from(dlc) > 64 {
+                return Err(ParseError::InvalidLength);
+            }
+            if buf.remaining() < usize::from(dlc) {
+                return Err(ParseError::UnexpectedEnd);
+            }
+            let mut data = [0u8; 64];
+            buf.copy_to_slice(&mut data[..usize::from(dlc)]);
+            let data = &data[..usize::from(dlc)];
+            if header & HEADER_FD != 0 {
+                let brs = header & HEADER_BRS != 0;
+                let esi = header & HEADER_ESI != 0;
+                Message::new_fd(id, data, brs, esi).map_err(|_| ParseError::InvalidLength)
+            } else {
+                Message::new(id, data).map_err(|_| ParseError::InvalidLength)
+            }
+        }
+    }
+}
+
+quick_error! {
+    /// Errors returned by `Message::read_from`
+    #[derive(Debug, PartialEq)]
+    pub enum ParseError {
+        /// `buf` did not contain enough bytes to parse a complete message
+        UnexpectedEnd {}
+        /// The header and ID bytes did not describe a valid standard or extended ID
+        InvalidId {}
+        /// The DLC byte was not a valid data length for the frame's type
+        InvalidLength {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_round_trip_classic() {
+        let message = Message::new(0x123u16, &[1, 2, 3]).unwrap();
+        let mut buf = BytesMut::new();
+        message.write_to(&mut buf);
+        let parsed = Message::read_from(&mut buf).unwrap();
+        assert_eq!(message, parsed);
+    }
+
+    #[test]
+    fn test_round_trip_extended() {
+        let message = Message::new(0x1abcdu32, &[9, 8, 7, 6, 5]).unwrap();
+        let mut buf = BytesMut::new();
+        message.write_to(&mut buf);
+        let parsed = Message::read_from(&mut buf).unwrap();
+        assert_eq!(message, parsed);
+    }
+
+    #[test]
+    fn test_round_trip_fd() {
+        let message = Message::new_fd(0x42u16, &[0u8; 32], true, false).unwrap();
+        let mut buf = BytesMut::new();
+        message.write_to(&mut buf);
+        let parsed = Message::read_from(&mut buf).unwrap();
+        assert_eq!(message, parsed);
+    }
+
+    #[test]
+    fn test_round_trip_remote() {
+        let message = Message::new_remote(0x42u16, 6).unwrap();
+        let mut buf = BytesMut::new();
+        message.write_to(&mut buf);
+        let parsed = Message::read_from(&mut buf).unwrap();
+        assert_eq!(message, parsed);
+    }
+
+    #[test]
+    fn test_read_from_empty() {
+        let mut buf = BytesMut::new();
+        assert_eq!(Err(ParseError::UnexpectedEnd), Message::read_from(&mut buf));
+    }
+
+    #[test]
+    fn test_read_from_truncated_payload() {
+        let message = Message::new(0x123u16, &[1, 2, 3]).unwrap();
+        let mut buf = BytesMut::new();
+        message.write_to(&mut buf);
+        buf.truncate(buf.len() - 1);
+        assert_eq!(Err(ParseError::UnexpectedEnd), Message::read_from(&mut buf));
+    }
+
+    #[test]
+    fn test_read_from_oversized_dlc_rejected() {
+        // Header for a classical data frame with a DLC well beyond the 64-byte maximum, followed
+        // by enough bytes that a naive length check alone wouldn't catch the problem
+        let mut buf = BytesMut::new();
+        buf.put_u8(0);
+        buf.put_u32(0x123);
+        buf.put_u8(200);
+        buf.put_slice(&[0u8; 200]);
+        assert_eq!(Err(ParseError::InvalidLength), Message::read_from(&mut buf));
+    }
+}